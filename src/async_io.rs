@@ -0,0 +1,186 @@
+//! Async variants of discovery and transmission, built on non-blocking
+//! sockets instead of a dedicated OS thread per device.
+//!
+//! `AsyncBeacon`/`AsyncSend` are not wired into any reactor: on `WouldBlock`
+//! they re-wake themselves immediately, so an executor polling them busy-spins
+//! until the socket is ready rather than truly parking. That's fine for a
+//! handful of devices; wire in a real reactor (mio, tokio) before using this
+//! at scale.
+
+use std::future::Future;
+use std::net::{SocketAddr, UdpSocket};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::device::{self, DeviceHeader};
+use crate::error::{Error, Result};
+
+/// Await sending one frame's worth of native PixelPusher pixel packets,
+/// framed the same way `PixelPusher::start`'s xmit thread does.
+pub async fn send_frame(
+    socket: &UdpSocket,
+    dest: SocketAddr,
+    frame: &[u8],
+    seq: u32,
+    strips_attached: u8,
+    max_strips_per_packet: u8,
+    pixels_per_strip: u16,
+) -> Result<()> {
+    for packet in device::build_packets(frame, seq, strips_attached, max_strips_per_packet, pixels_per_strip) {
+        AsyncSend::new(socket, packet, dest).await?;
+    }
+    Ok(())
+}
+
+/// Await sending one frame's worth of Art-Net ArtDMX frames, framed the same
+/// way `PixelPusher::start`'s xmit thread does.
+pub async fn send_artnet_frame(
+    socket: &UdpSocket,
+    dest: SocketAddr,
+    frame: &[u8],
+    sequence: u8,
+    strips_attached: u8,
+    pixels_per_strip: u16,
+    base_universe: u16,
+) -> Result<()> {
+    for packet in device::build_artnet_packets(frame, sequence, strips_attached, pixels_per_strip, base_universe) {
+        AsyncSend::new(socket, packet, dest).await?;
+    }
+    Ok(())
+}
+
+/// Awaits the next beacon datagram and parses it into a `DeviceHeader`.
+pub struct AsyncBeacon {
+    socket: UdpSocket,
+    buf: [u8; 84],
+}
+
+impl AsyncBeacon {
+    /// Bind a non-blocking socket to `addr` (e.g. `"0.0.0.0:7331"`) to await
+    /// beacons on.
+    pub fn bind(addr: &str) -> Result<AsyncBeacon> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(AsyncBeacon { socket, buf: [0; 84] })
+    }
+}
+
+impl Future for AsyncBeacon {
+    type Output = Result<Box<dyn DeviceHeader + Send>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.socket.recv_from(&mut this.buf) {
+            Ok((amt, _src)) => Poll::Ready(device::parse_header(&this.buf[..amt])),
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(Error::from(e))),
+        }
+    }
+}
+
+/// Awaits a single non-blocking send of `packet` to `dest`.
+pub struct AsyncSend<'a> {
+    socket: &'a UdpSocket,
+    packet: Vec<u8>,
+    dest: SocketAddr,
+}
+
+impl<'a> AsyncSend<'a> {
+    pub fn new(socket: &'a UdpSocket, packet: Vec<u8>, dest: SocketAddr) -> AsyncSend<'a> {
+        AsyncSend { socket, packet, dest }
+    }
+}
+
+impl<'a> Future for AsyncSend<'a> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.socket.send_to(&self.packet, self.dest) {
+            Ok(sent) => Poll::Ready(Ok(sent)),
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(Error::from(e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    fn noop_waker() -> Waker {
+        fn no_op(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+        unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = Box::pin(fut);
+        loop {
+            if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[test]
+    fn async_beacon_pends_until_a_datagram_arrives() {
+        let mut beacon = AsyncBeacon::bind("127.0.0.1:0").unwrap();
+        let addr = beacon.socket.local_addr().unwrap();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(matches!(Pin::new(&mut beacon).poll(&mut cx), Poll::Pending));
+
+        // A minimal 24-byte base header with an unrecognized device type.
+        let mut datagram = vec![0u8; 24];
+        datagram[10] = 99;
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(&datagram, addr).unwrap();
+
+        match Pin::new(&mut beacon).poll(&mut cx) {
+            Poll::Ready(Ok(header)) => assert_eq!(header.device_type(), device::DeviceType::UNKNOWN),
+            other => panic!("expected Ready(Ok(_)), got pending={}", matches!(other, Poll::Pending)),
+        }
+    }
+
+    #[test]
+    fn async_send_reports_bytes_sent() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = receiver.local_addr().unwrap();
+
+        let sent = block_on(AsyncSend::new(&socket, vec![1, 2, 3], dest)).unwrap();
+        assert_eq!(sent, 3);
+    }
+
+    #[test]
+    fn send_frame_delivers_one_packet_per_strip() {
+        // 8 strips * 480 pixels/strip * 3 bytes/pixel, PixelPusher's fixed framebuffer size.
+        let frame = vec![0u8; 8 * 480 * 3];
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = receiver.local_addr().unwrap();
+
+        block_on(send_frame(&socket, dest, &frame, 1, 2, 1, 1)).unwrap();
+
+        let mut buf = [0u8; 64];
+        for _ in 0..2 {
+            let (amt, _) = receiver.recv_from(&mut buf).unwrap();
+            assert!(amt > 0);
+        }
+    }
+}
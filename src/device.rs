@@ -1,10 +1,16 @@
 use std::io::Cursor;
-use std::net::Ipv4Addr;
-use std::thread::Thread;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use hwaddr::HwAddr;
 use image::Rgb;
+use log::warn;
+
+use crate::error::{Error, Result};
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum DeviceType {
@@ -18,7 +24,22 @@ pub trait DeviceHeader {
     fn hw_addr(&self) -> HwAddr;
     fn ip_addr(&self) -> Ipv4Addr;
     fn device_type(&self) -> DeviceType;
-    fn serialize(&self, wtr: &Vec<u8>);
+    fn serialize(&self, wtr: &mut Vec<u8>) -> Result<()>;
+
+    /// Sequence delta from the beacon, if this device type reports one.
+    fn delta_sequence(&self) -> Option<u32> {
+        None
+    }
+
+    /// Power draw in milliwatts from the beacon, if this device type reports one.
+    fn power_total(&self) -> Option<u32> {
+        None
+    }
+
+    /// Requested update period in microseconds, if this device type reports one.
+    fn update_period(&self) -> Option<u32> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -47,29 +68,28 @@ impl DeviceHeader for Header {
         DeviceType::UNKNOWN
     }
 
-    fn serialize(&self, wtr: &Vec<u8>) {
-        let mut w = wtr.to_owned();
-        w.extend(self.hw_addr.octets().iter());
-        w.extend(self.ip_addr.octets().iter());
+    fn serialize(&self, wtr: &mut Vec<u8>) -> Result<()> {
+        wtr.extend(self.hw_addr.octets().iter());
+        wtr.extend(self.ip_addr.octets().iter());
         let device_type = match self.device_type {
             DeviceType::ETHERDREAM => 0,
             DeviceType::LUMIABRIDGE => 1,
             DeviceType::PIXELPUSHER => 2,
             DeviceType::UNKNOWN => 99,
-            _ => 99
         };
-        w.push(device_type);
-        w.push(self.protocol_version);
-        w.write_u16::<LittleEndian>(self.vendor_id).unwrap();
-        w.write_u16::<LittleEndian>(self.product_id).unwrap();
-        w.write_u16::<LittleEndian>(self.hw_revision).unwrap();
-        w.write_u16::<LittleEndian>(self.sw_revision).unwrap();
-        w.write_u32::<LittleEndian>(self.link_speed).unwrap();
+        wtr.push(device_type);
+        wtr.push(self.protocol_version);
+        wtr.write_u16::<LittleEndian>(self.vendor_id)?;
+        wtr.write_u16::<LittleEndian>(self.product_id)?;
+        wtr.write_u16::<LittleEndian>(self.hw_revision)?;
+        wtr.write_u16::<LittleEndian>(self.sw_revision)?;
+        wtr.write_u32::<LittleEndian>(self.link_speed)?;
+        Ok(())
     }
 }
 
 #[derive(Debug)]
-struct PixelPusherHeader {
+pub struct PixelPusherHeader {
     base_header: Header,
     strips_attached: u8,
     max_strips_per_packet: u8,
@@ -97,42 +117,63 @@ impl DeviceHeader for PixelPusherHeader {
         DeviceType::PIXELPUSHER
     }
 
-    fn serialize(&self, wtr: &Vec<u8>) {
-        self.base_header.serialize(wtr);
-        let mut w = wtr.to_owned();
-        w.push(self.strips_attached);
-        w.push(self.max_strips_per_packet);
-        w.write_u16::<LittleEndian>(self.pixels_per_strip).unwrap();
-        w.write_u32::<LittleEndian>(self.update_period).unwrap();
-        w.write_u32::<LittleEndian>(self.power_total).unwrap();
-        w.write_u32::<LittleEndian>(self.delta_sequence).unwrap();
-        w.write_u32::<LittleEndian>(self.controller).unwrap();
-        w.write_u32::<LittleEndian>(self.group).unwrap();
-        w.write_u16::<LittleEndian>(self.artnet_universe).unwrap();
-        w.write_u16::<LittleEndian>(self.artnet_channel).unwrap();
-        w.write_u16::<LittleEndian>(self.my_port).unwrap();
+    fn delta_sequence(&self) -> Option<u32> {
+        Some(self.delta_sequence)
+    }
+
+    fn power_total(&self) -> Option<u32> {
+        Some(self.power_total)
+    }
+
+    fn update_period(&self) -> Option<u32> {
+        Some(self.update_period)
+    }
+
+    fn serialize(&self, wtr: &mut Vec<u8>) -> Result<()> {
+        self.base_header.serialize(wtr)?;
+        wtr.push(self.strips_attached);
+        wtr.push(self.max_strips_per_packet);
+        wtr.write_u16::<LittleEndian>(self.pixels_per_strip)?;
+        wtr.write_u32::<LittleEndian>(self.update_period)?;
+        wtr.write_u32::<LittleEndian>(self.power_total)?;
+        wtr.write_u32::<LittleEndian>(self.delta_sequence)?;
+        wtr.write_u32::<LittleEndian>(self.controller)?;
+        wtr.write_u32::<LittleEndian>(self.group)?;
+        wtr.write_u16::<LittleEndian>(self.artnet_universe)?;
+        wtr.write_u16::<LittleEndian>(self.artnet_channel)?;
+        wtr.write_u16::<LittleEndian>(self.my_port)?;
+        Ok(())
     }
 }
 
-pub fn parse_header(buf: [u8; 84]) -> Box<dyn DeviceHeader + Send> {
+/// Minimum bytes needed for the device-agnostic header fields.
+const BASE_HEADER_LEN: usize = 24;
+
+pub fn parse_header(buf: &[u8]) -> Result<Box<dyn DeviceHeader + Send>> {
+    if buf.len() < BASE_HEADER_LEN {
+        return Err(Error::Truncated {
+            expected: BASE_HEADER_LEN,
+            actual: buf.len(),
+        });
+    }
     let hw_addr = HwAddr::from(&buf[0..6]);
-    let mut rdr = Cursor::new(&buf[..]);
+    let mut rdr = Cursor::new(buf);
     rdr.set_position(6);
-    let ipu32 = rdr.read_u32::<LittleEndian>().unwrap();
+    let ipu32 = rdr.read_u32::<LittleEndian>()?;
     let ip_addr = Ipv4Addr::from(ipu32);
-    let device_type_u8 = rdr.read_u8().unwrap();
+    let device_type_u8 = rdr.read_u8()?;
     let device_type = match device_type_u8 {
         0 => DeviceType::ETHERDREAM,
         1 => DeviceType::LUMIABRIDGE,
         2 => DeviceType::PIXELPUSHER,
         _ => DeviceType::UNKNOWN,
     };
-    let protocol_version = rdr.read_u8().unwrap();
-    let vendor_id = rdr.read_u16::<LittleEndian>().unwrap();
-    let product_id = rdr.read_u16::<LittleEndian>().unwrap();
-    let hw_revision = rdr.read_u16::<LittleEndian>().unwrap();
-    let sw_revision = rdr.read_u16::<LittleEndian>().unwrap();
-    let link_speed = rdr.read_u32::<LittleEndian>().unwrap();
+    let protocol_version = rdr.read_u8()?;
+    let vendor_id = rdr.read_u16::<LittleEndian>()?;
+    let product_id = rdr.read_u16::<LittleEndian>()?;
+    let hw_revision = rdr.read_u16::<LittleEndian>()?;
+    let sw_revision = rdr.read_u16::<LittleEndian>()?;
+    let link_speed = rdr.read_u32::<LittleEndian>()?;
     let base_header = Header {
         hw_addr,
         ip_addr,
@@ -145,26 +186,54 @@ pub fn parse_header(buf: [u8; 84]) -> Box<dyn DeviceHeader + Send> {
         link_speed,
     };
     match device_type {
-        DeviceType::PIXELPUSHER => return Box::from(parse_pixelpusher_header(base_header, buf)),
-        _ => return Box::from(base_header),
-    }
-}
-
-fn parse_pixelpusher_header(base_header: Header, buf: [u8; 84]) -> PixelPusherHeader {
-    let mut rdr = Cursor::new(&buf[..]);
-    rdr.set_position(24);
-    let strips_attached = rdr.read_u8().unwrap();
-    let max_strips_per_packet = rdr.read_u8().unwrap();
-    let pixels_per_strip = rdr.read_u16::<LittleEndian>().unwrap();
-    let update_period = rdr.read_u32::<LittleEndian>().unwrap();
-    let power_total = rdr.read_u32::<LittleEndian>().unwrap();
-    let delta_sequence = rdr.read_u32::<LittleEndian>().unwrap();
-    let controller = rdr.read_u32::<LittleEndian>().unwrap();
-    let group = rdr.read_u32::<LittleEndian>().unwrap();
-    let artnet_universe = rdr.read_u16::<LittleEndian>().unwrap();
-    let artnet_channel = rdr.read_u16::<LittleEndian>().unwrap();
-    let my_port = rdr.read_u16::<LittleEndian>().unwrap();
-    PixelPusherHeader {
+        DeviceType::PIXELPUSHER => {
+            Ok(Box::from(parse_pixelpusher_header(base_header, buf)?))
+        }
+        _ => Ok(Box::from(base_header)),
+    }
+}
+
+/// Minimum bytes needed once the PixelPusher-specific fields are included.
+const PIXELPUSHER_HEADER_LEN: usize = BASE_HEADER_LEN + 30;
+
+fn parse_pixelpusher_header(base_header: Header, buf: &[u8]) -> Result<PixelPusherHeader> {
+    if buf.len() < PIXELPUSHER_HEADER_LEN {
+        return Err(Error::Truncated {
+            expected: PIXELPUSHER_HEADER_LEN,
+            actual: buf.len(),
+        });
+    }
+    let mut rdr = Cursor::new(buf);
+    rdr.set_position(BASE_HEADER_LEN as u64);
+    let strips_attached = rdr.read_u8()?;
+    if strips_attached > MAX_STRIPS {
+        return Err(Error::InvalidField(format!(
+            "strips_attached {} exceeds the {} strips the framebuffer supports",
+            strips_attached, MAX_STRIPS
+        )));
+    }
+    let max_strips_per_packet = rdr.read_u8()?;
+    if max_strips_per_packet == 0 {
+        return Err(Error::InvalidField(
+            "max_strips_per_packet must be at least 1".to_string(),
+        ));
+    }
+    let pixels_per_strip = rdr.read_u16::<LittleEndian>()?;
+    if pixels_per_strip as usize > STRIP_STRIDE_PIXELS {
+        return Err(Error::InvalidField(format!(
+            "pixels_per_strip {} exceeds the {} pixels per strip the framebuffer supports",
+            pixels_per_strip, STRIP_STRIDE_PIXELS
+        )));
+    }
+    let update_period = rdr.read_u32::<LittleEndian>()?;
+    let power_total = rdr.read_u32::<LittleEndian>()?;
+    let delta_sequence = rdr.read_u32::<LittleEndian>()?;
+    let controller = rdr.read_u32::<LittleEndian>()?;
+    let group = rdr.read_u32::<LittleEndian>()?;
+    let artnet_universe = rdr.read_u16::<LittleEndian>()?;
+    let artnet_channel = rdr.read_u16::<LittleEndian>()?;
+    let my_port = rdr.read_u16::<LittleEndian>()?;
+    Ok(PixelPusherHeader {
         base_header,
         strips_attached,
         max_strips_per_packet,
@@ -177,22 +246,500 @@ fn parse_pixelpusher_header(base_header: Header, buf: [u8; 84]) -> PixelPusherHe
         artnet_universe,
         artnet_channel,
         my_port,
-    }
+    })
+}
+
+/// Pixels reserved per strip inside `PixelPusher::buffer`.
+const STRIP_STRIDE_PIXELS: usize = 480;
+
+/// Number of strip slots reserved inside `PixelPusher::buffer`.
+const MAX_STRIPS: u8 = 8;
+
+/// Sequence value that marks a packet as a command, not pixel data.
+const COMMAND_SEQUENCE_MARKER: u32 = 0xFFFF_FFFF;
+
+/// Magic cookie that follows the sequence marker in a command packet.
+const COMMAND_MAGIC: [u8; 16] = [
+    0x40, 0x09, 0x2d, 0xa6, 0x15, 0xa5, 0xdd, 0xe5, 0x6a, 0x9d, 0x4d, 0x5a, 0xcf, 0x09, 0xaf, 0x50,
+];
+
+const CMD_RESET: u8 = 1;
+const CMD_GLOBAL_BRIGHTNESS_SET: u8 = 2;
+const CMD_LED_CONFIGURE: u8 = 4;
+const CMD_STRIP_BRIGHTNESS_SET: u8 = 5;
+
+/// LED strip chipset, used by `PixelPusher::configure_strip`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum StripType {
+    LPD8806,
+    WS2801,
+    WS2811,
+    APA102,
+}
+
+/// RGB channel ordering on the wire, used by `PixelPusher::configure_strip`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ColorOrder {
+    RGB,
+    RBG,
+    GRB,
+    GBR,
+    BRG,
+    BGR,
+}
+
+/// Which wire protocol `PixelPusher::start` speaks when transmitting `buffer`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OutputMode {
+    /// Native PixelPusher pixel packets, paced to `update_period`.
+    PixelPusher,
+    /// Standard Art-Net ArtDMX frames, starting at `header.artnet_universe`.
+    ArtNet,
 }
 
+/// Eight-byte Art-Net packet identifier that opens every Art-Net frame.
+const ARTNET_ID: [u8; 8] = *b"Art-Net\0";
+const ARTNET_OPCODE_DMX: u16 = 0x5000;
+const ARTNET_PROTOCOL_VERSION: u16 = 14;
+const ARTNET_MAX_CHANNELS_PER_UNIVERSE: usize = 512;
+
 pub struct PixelPusher {
     header: PixelPusherHeader,
-    buffer: [u8; 480 * 8 * 3],
-    xmit_thread: Thread,
-    update_thread: Thread,
+    buffer: Arc<Mutex<[u8; STRIP_STRIDE_PIXELS * 8 * 3]>>,
+    sequence: Arc<AtomicU32>,
+    running: Arc<AtomicBool>,
+    xmit_thread: Option<JoinHandle<()>>,
+    /// Microseconds the last frame took, used to derive `achieved_fps`.
+    last_frame_micros: Arc<AtomicU64>,
+    dropped_frames: Arc<AtomicU64>,
+    control_socket: UdpSocket,
+    output_mode: OutputMode,
 }
 
 impl PixelPusher {
-    pub fn set_color(&mut self, strip: u8, pixel: u8, color: Rgb<u8>) {
-        let x = &mut self.buffer;
-        let index = (480 * 3 * (strip as usize)) + (pixel as usize * 3);
-        x[index] = color.data[0];
-        x[index + 1] = color.data[1];
-        x[index + 2] = color.data[2];
+    pub fn new(header: PixelPusherHeader, output_mode: OutputMode) -> Result<PixelPusher> {
+        let control_socket = UdpSocket::bind("0.0.0.0:0")?;
+        Ok(PixelPusher {
+            header,
+            buffer: Arc::new(Mutex::new([0; STRIP_STRIDE_PIXELS * 8 * 3])),
+            sequence: Arc::new(AtomicU32::new(0)),
+            running: Arc::new(AtomicBool::new(false)),
+            xmit_thread: None,
+            last_frame_micros: Arc::new(AtomicU64::new(0)),
+            dropped_frames: Arc::new(AtomicU64::new(0)),
+            control_socket,
+            output_mode,
+        })
+    }
+
+    /// Frame rate actually achieved, `0.0` until the first frame completes.
+    pub fn achieved_fps(&self) -> f64 {
+        let micros = self.last_frame_micros.load(Ordering::SeqCst);
+        if micros == 0 {
+            0.0
+        } else {
+            1_000_000.0 / micros as f64
+        }
+    }
+
+    /// Count of frames that overran `update_period` and couldn't be paced.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::SeqCst)
+    }
+
+    /// Sequence number the next transmitted packet will carry. Unlike
+    /// `header.delta_sequence`, this tracks the running xmit counter live.
+    pub fn delta_sequence(&self) -> u32 {
+        self.sequence.load(Ordering::SeqCst)
+    }
+
+    /// Set the brightness applied to every strip on the controller.
+    pub fn set_global_brightness(&self, brightness: u16) -> Result<()> {
+        let mut params = Vec::new();
+        params.write_u16::<LittleEndian>(brightness).unwrap();
+        self.send_command(CMD_GLOBAL_BRIGHTNESS_SET, &params)
+    }
+
+    /// Set the brightness applied to a single strip.
+    pub fn set_strip_brightness(&self, strip: u8, brightness: u16) -> Result<()> {
+        self.check_strip(strip)?;
+        let mut params = vec![strip];
+        params.write_u16::<LittleEndian>(brightness).unwrap();
+        self.send_command(CMD_STRIP_BRIGHTNESS_SET, &params)
+    }
+
+    /// Configure a strip's chipset and color order.
+    pub fn configure_strip(
+        &self,
+        strip: u8,
+        strip_type: StripType,
+        color_order: ColorOrder,
+    ) -> Result<()> {
+        self.check_strip(strip)?;
+        let strip_type_byte = match strip_type {
+            StripType::LPD8806 => 0,
+            StripType::WS2801 => 1,
+            StripType::WS2811 => 2,
+            StripType::APA102 => 3,
+        };
+        let color_order_byte = match color_order {
+            ColorOrder::RGB => 0,
+            ColorOrder::RBG => 1,
+            ColorOrder::GRB => 2,
+            ColorOrder::GBR => 3,
+            ColorOrder::BRG => 4,
+            ColorOrder::BGR => 5,
+        };
+        let params = vec![strip, strip_type_byte, color_order_byte];
+        self.send_command(CMD_LED_CONFIGURE, &params)
+    }
+
+    /// Ask the controller to reset itself.
+    pub fn reset(&self) -> Result<()> {
+        self.send_command(CMD_RESET, &[])
+    }
+
+    fn check_strip(&self, strip: u8) -> Result<()> {
+        if strip >= self.header.strips_attached {
+            Err(Error::StripOutOfRange(strip))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn send_command(&self, command: u8, params: &[u8]) -> Result<()> {
+        if self.output_mode != OutputMode::PixelPusher {
+            return Err(Error::Unsupported(
+                "command packets are not supported when driving Art-Net".to_string(),
+            ));
+        }
+        let mut packet = Vec::with_capacity(4 + COMMAND_MAGIC.len() + 1 + params.len());
+        packet.write_u32::<LittleEndian>(COMMAND_SEQUENCE_MARKER).unwrap();
+        packet.extend_from_slice(&COMMAND_MAGIC);
+        packet.push(command);
+        packet.extend_from_slice(params);
+
+        let dest = SocketAddr::from((self.header.base_header.ip_addr, self.header.my_port));
+        self.control_socket.send_to(&packet, dest)?;
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, strip: u8, pixel: u8, color: Rgb<u8>) -> Result<()> {
+        self.check_strip(strip)?;
+        let mut buf = self.buffer.lock().unwrap();
+        let index = (STRIP_STRIDE_PIXELS * 3 * (strip as usize)) + (pixel as usize * 3);
+        buf[index] = color.data[0];
+        buf[index + 1] = color.data[1];
+        buf[index + 2] = color.data[2];
+        Ok(())
+    }
+
+    /// Spawn the background thread that packetizes `buffer` and sends it to
+    /// the controller at `header.base_header.ip_addr:header.my_port`.
+    pub fn start(&mut self) {
+        if self.xmit_thread.is_some() {
+            return;
+        }
+        self.running.store(true, Ordering::SeqCst);
+
+        let buffer = Arc::clone(&self.buffer);
+        let sequence = Arc::clone(&self.sequence);
+        let running = Arc::clone(&self.running);
+        let last_frame_micros = Arc::clone(&self.last_frame_micros);
+        let dropped_frames = Arc::clone(&self.dropped_frames);
+        let dest = SocketAddr::from((self.header.base_header.ip_addr, self.header.my_port));
+        let strips_attached = self.header.strips_attached;
+        let max_strips_per_packet = self.header.max_strips_per_packet;
+        let pixels_per_strip = self.header.pixels_per_strip;
+        let artnet_universe = self.header.artnet_universe;
+        let update_period = Duration::from_micros(self.header.update_period as u64);
+        let output_mode = self.output_mode;
+
+        self.xmit_thread = Some(thread::spawn(move || {
+            let socket = match UdpSocket::bind("0.0.0.0:0") {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("failed to bind PixelPusher xmit socket: {}", e);
+                    return;
+                }
+            };
+
+            let mut last_send = Instant::now();
+            while running.load(Ordering::SeqCst) {
+                let seq = sequence.fetch_add(1, Ordering::SeqCst);
+                let frame = buffer.lock().unwrap();
+                // All packets making up this frame go out back-to-back; we
+                // only pace at the frame boundary below, not between packets.
+                let packets = match output_mode {
+                    OutputMode::PixelPusher => {
+                        build_packets(&frame[..], seq, strips_attached, max_strips_per_packet, pixels_per_strip)
+                    }
+                    OutputMode::ArtNet => build_artnet_packets(
+                        &frame[..],
+                        seq as u8,
+                        strips_attached,
+                        pixels_per_strip,
+                        artnet_universe,
+                    ),
+                };
+                for packet in packets {
+                    if let Err(e) = socket.send_to(&packet, dest) {
+                        warn!("failed to send PixelPusher packet: {}", e);
+                    }
+                }
+                drop(frame);
+
+                let elapsed = last_send.elapsed();
+                if elapsed < update_period {
+                    thread::sleep(update_period - elapsed);
+                } else if update_period > Duration::from_micros(0) {
+                    dropped_frames.fetch_add(1, Ordering::SeqCst);
+                }
+                last_frame_micros.store(last_send.elapsed().as_micros() as u64, Ordering::SeqCst);
+                last_send = Instant::now();
+            }
+        }));
+    }
+
+    /// Stop the transmit thread and wait for it to exit.
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.xmit_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for PixelPusher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Slice `frame` into PixelPusher pixel packets of up to
+/// `max_strips_per_packet` strip records each.
+pub(crate) fn build_packets(
+    frame: &[u8],
+    seq: u32,
+    strips_attached: u8,
+    max_strips_per_packet: u8,
+    pixels_per_strip: u16,
+) -> Vec<Vec<u8>> {
+    let mut packets = Vec::new();
+    let bytes_per_strip = pixels_per_strip as usize * 3;
+    let mut strip = 0u8;
+    while strip < strips_attached {
+        let mut packet = Vec::with_capacity(4 + max_strips_per_packet as usize * (1 + bytes_per_strip));
+        packet.write_u32::<LittleEndian>(seq).unwrap();
+
+        let mut strips_in_packet = 0u8;
+        while strip < strips_attached && strips_in_packet < max_strips_per_packet {
+            let offset = STRIP_STRIDE_PIXELS * 3 * (strip as usize);
+            packet.push(strip);
+            packet.extend_from_slice(&frame[offset..offset + bytes_per_strip]);
+            strip += 1;
+            strips_in_packet += 1;
+        }
+        packets.push(packet);
+    }
+    packets
+}
+
+/// Concatenate every strip's pixel data into one channel stream and slice
+/// it into Art-Net ArtDMX frames, rolling into successive universes from
+/// `base_universe`.
+pub(crate) fn build_artnet_packets(
+    frame: &[u8],
+    sequence: u8,
+    strips_attached: u8,
+    pixels_per_strip: u16,
+    base_universe: u16,
+) -> Vec<Vec<u8>> {
+    let bytes_per_strip = pixels_per_strip as usize * 3;
+    let mut channels = Vec::with_capacity(strips_attached as usize * bytes_per_strip);
+    for strip in 0..strips_attached {
+        let offset = STRIP_STRIDE_PIXELS * 3 * (strip as usize);
+        channels.extend_from_slice(&frame[offset..offset + bytes_per_strip]);
+    }
+
+    channels
+        .chunks(ARTNET_MAX_CHANNELS_PER_UNIVERSE)
+        .enumerate()
+        .map(|(i, data)| {
+            let mut packet = Vec::with_capacity(18 + data.len());
+            packet.extend_from_slice(&ARTNET_ID);
+            packet.write_u16::<LittleEndian>(ARTNET_OPCODE_DMX).unwrap();
+            packet.write_u16::<BigEndian>(ARTNET_PROTOCOL_VERSION).unwrap();
+            packet.push(sequence);
+            packet.push(0); // physical input port, unused
+            packet
+                .write_u16::<LittleEndian>(base_universe.wrapping_add(i as u16))
+                .unwrap();
+            packet.write_u16::<BigEndian>(data.len() as u16).unwrap();
+            packet.extend_from_slice(data);
+            packet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_header(strips_attached: u8) -> PixelPusherHeader {
+        let base_header = Header {
+            hw_addr: HwAddr::from(&[0, 1, 2, 3, 4, 5][..]),
+            ip_addr: Ipv4Addr::new(10, 0, 0, 7),
+            device_type: DeviceType::PIXELPUSHER,
+            protocol_version: 1,
+            vendor_id: 0,
+            product_id: 0,
+            hw_revision: 0,
+            sw_revision: 0,
+            link_speed: 100_000_000,
+        };
+        PixelPusherHeader {
+            base_header,
+            strips_attached,
+            max_strips_per_packet: 1,
+            pixels_per_strip: 1,
+            update_period: 16_666,
+            power_total: 0,
+            delta_sequence: 0,
+            controller: 0,
+            group: 0,
+            artnet_universe: 0,
+            artnet_channel: 0,
+            my_port: 9897,
+        }
+    }
+
+    fn valid_pixelpusher_header_bytes() -> Vec<u8> {
+        let mut buf = Vec::new();
+        test_header(2).serialize(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn parse_header_rejects_short_buffers() {
+        match parse_header(&[0u8; BASE_HEADER_LEN - 1]) {
+            Err(Error::Truncated { expected, actual }) => {
+                assert_eq!(expected, BASE_HEADER_LEN);
+                assert_eq!(actual, BASE_HEADER_LEN - 1);
+            }
+            other => panic!("expected Truncated, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn parse_pixelpusher_header_rejects_short_buffers() {
+        let mut buf = valid_pixelpusher_header_bytes();
+        buf.truncate(PIXELPUSHER_HEADER_LEN - 1);
+        match parse_header(&buf) {
+            Err(Error::Truncated { expected, actual }) => {
+                assert_eq!(expected, PIXELPUSHER_HEADER_LEN);
+                assert_eq!(actual, PIXELPUSHER_HEADER_LEN - 1);
+            }
+            other => panic!("expected Truncated, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn parse_pixelpusher_header_accepts_valid_fields() {
+        let buf = valid_pixelpusher_header_bytes();
+        let header = parse_header(&buf).unwrap();
+        assert_eq!(header.device_type(), DeviceType::PIXELPUSHER);
+    }
+
+    #[test]
+    fn parse_pixelpusher_header_rejects_too_many_strips() {
+        let mut buf = valid_pixelpusher_header_bytes();
+        buf[BASE_HEADER_LEN] = MAX_STRIPS + 1; // strips_attached
+        match parse_header(&buf) {
+            Err(Error::InvalidField(_)) => {}
+            other => panic!("expected InvalidField, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn parse_pixelpusher_header_rejects_zero_max_strips_per_packet() {
+        let mut buf = valid_pixelpusher_header_bytes();
+        buf[BASE_HEADER_LEN + 1] = 0; // max_strips_per_packet
+        match parse_header(&buf) {
+            Err(Error::InvalidField(_)) => {}
+            other => panic!("expected InvalidField, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn parse_pixelpusher_header_rejects_oversized_pixels_per_strip() {
+        let mut buf = valid_pixelpusher_header_bytes();
+        let too_many = (STRIP_STRIDE_PIXELS + 1) as u16;
+        buf[BASE_HEADER_LEN + 2..BASE_HEADER_LEN + 4].copy_from_slice(&too_many.to_le_bytes());
+        match parse_header(&buf) {
+            Err(Error::InvalidField(_)) => {}
+            other => panic!("expected InvalidField, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn build_packets_splits_on_max_strips_per_packet() {
+        let mut frame = vec![0u8; STRIP_STRIDE_PIXELS * 8 * 3];
+        frame[0] = 1; // strip 0, red channel
+        frame[STRIP_STRIDE_PIXELS * 3] = 2; // strip 1, red channel
+        frame[STRIP_STRIDE_PIXELS * 3 * 2] = 3; // strip 2, red channel
+
+        let packets = build_packets(&frame, 42, 3, 2, 1);
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(&packets[0][0..4], &42u32.to_le_bytes());
+        // strip 0: index byte + 1 RGB pixel
+        assert_eq!(packets[0][4], 0);
+        assert_eq!(&packets[0][5..8], &[1, 0, 0]);
+        // strip 1 packed into the same packet
+        assert_eq!(packets[0][8], 1);
+        assert_eq!(&packets[0][9..12], &[2, 0, 0]);
+
+        assert_eq!(&packets[1][0..4], &42u32.to_le_bytes());
+        assert_eq!(packets[1][4], 2);
+        assert_eq!(&packets[1][5..8], &[3, 0, 0]);
+    }
+
+    #[test]
+    fn build_artnet_packets_rolls_over_universes() {
+        let mut frame = vec![0u8; STRIP_STRIDE_PIXELS * 8 * 3];
+        frame[0] = 9;
+
+        let packets = build_artnet_packets(&frame, 7, 1, 200, 3);
+
+        // 200 pixels * 3 bytes = 600 channels, split 512 + 88.
+        assert_eq!(packets.len(), 2);
+        assert_eq!(&packets[0][0..8], &ARTNET_ID);
+        assert_eq!(&packets[0][18..21], &[9, 0, 0]);
+        assert_eq!(packets[0][12], 7); // sequence
+        let universe0 = u16::from_le_bytes([packets[0][14], packets[0][15]]);
+        let len0 = u16::from_be_bytes([packets[0][16], packets[0][17]]);
+        assert_eq!(universe0, 3);
+        assert_eq!(len0, 512);
+
+        let universe1 = u16::from_le_bytes([packets[1][14], packets[1][15]]);
+        let len1 = u16::from_be_bytes([packets[1][16], packets[1][17]]);
+        assert_eq!(universe1, 4);
+        assert_eq!(len1, 88);
+    }
+
+    #[test]
+    fn set_color_rejects_out_of_range_strip() {
+        let mut pusher = PixelPusher::new(test_header(2), OutputMode::PixelPusher).unwrap();
+        match pusher.set_color(9, 0, Rgb([1, 2, 3])) {
+            Err(Error::StripOutOfRange(9)) => {}
+            other => panic!("expected StripOutOfRange(9), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn set_color_accepts_attached_strip() {
+        let mut pusher = PixelPusher::new(test_header(2), OutputMode::PixelPusher).unwrap();
+        assert!(pusher.set_color(1, 0, Rgb([1, 2, 3])).is_ok());
     }
 }
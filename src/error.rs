@@ -0,0 +1,54 @@
+use std::fmt;
+use std::io;
+
+/// Crate-wide error type covering malformed packets, out-of-range
+/// arguments, and the underlying I/O failures sockets can surface.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying socket operation failed.
+    Io(io::Error),
+    /// A datagram was too short to contain the field being read.
+    Truncated { expected: usize, actual: usize },
+    /// `strip` is not among the controller's `strips_attached`.
+    StripOutOfRange(u8),
+    /// The requested operation is not supported in the current mode.
+    Unsupported(String),
+    /// A header field was parsed successfully but its value is unusable
+    /// (e.g. zero, or beyond what the fixed-size framebuffer supports).
+    InvalidField(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Truncated { expected, actual } => write!(
+                f,
+                "truncated packet: expected at least {} bytes, got {}",
+                expected, actual
+            ),
+            Error::StripOutOfRange(strip) => {
+                write!(f, "strip {} is not attached to this controller", strip)
+            }
+            Error::Unsupported(msg) => write!(f, "unsupported operation: {}", msg),
+            Error::InvalidField(msg) => write!(f, "invalid header field: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
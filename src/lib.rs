@@ -7,6 +7,8 @@ pub mod discovery;
 pub mod canvas;
 pub mod registry;
 pub mod device;
+pub mod error;
+pub mod async_io;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use hwaddr::HwAddr;
+use log::{trace, warn};
+
+use crate::device;
+use crate::device::DeviceType;
+use crate::error::Result;
+
+/// Default staleness window: PixelPushers rebroadcast roughly once a second.
+pub const DEFAULT_STALENESS: Duration = Duration::from_secs(3);
+
+/// Latest known state of a device, updated as its beacons arrive.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    pub hw_addr: HwAddr,
+    pub ip_addr: Ipv4Addr,
+    pub device_type: DeviceType,
+    pub delta_sequence: Option<u32>,
+    pub power_total: Option<u32>,
+    pub update_period: Option<u32>,
+    last_seen: Instant,
+}
+
+/// Events emitted by `Registry` as devices join, rebroadcast, or go quiet.
+#[derive(Debug, Clone)]
+pub enum RegistryEvent {
+    DeviceAdded(DeviceInfo),
+    DeviceUpdated(DeviceInfo),
+    DeviceRemoved(HwAddr),
+}
+
+/// Long-running, continuously-updated view of the devices visible on the
+/// network, built from their periodic beacons.
+pub struct Registry {
+    devices: Arc<Mutex<HashMap<HwAddr, DeviceInfo>>>,
+    running: Arc<AtomicBool>,
+    listener_thread: Option<JoinHandle<()>>,
+    sweeper_thread: Option<JoinHandle<()>>,
+}
+
+impl Registry {
+    /// Bind the shared discovery socket and start tracking devices, expiring
+    /// any that go `staleness` without a beacon.
+    pub fn start(staleness: Duration) -> Result<(Registry, Receiver<RegistryEvent>)> {
+        let socket = UdpSocket::bind("0.0.0.0:7331")?;
+        socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+
+        let devices: Arc<Mutex<HashMap<HwAddr, DeviceInfo>>> = Arc::new(Mutex::new(HashMap::new()));
+        let running = Arc::new(AtomicBool::new(true));
+        let (events_tx, events_rx) = channel();
+
+        let listener_thread = spawn_listener(socket, Arc::clone(&devices), Arc::clone(&running), events_tx.clone());
+        let sweeper_thread = spawn_sweeper(staleness, Arc::clone(&devices), Arc::clone(&running), events_tx);
+
+        Ok((
+            Registry {
+                devices,
+                running,
+                listener_thread: Some(listener_thread),
+                sweeper_thread: Some(sweeper_thread),
+            },
+            events_rx,
+        ))
+    }
+
+    /// Snapshot of every device currently considered live.
+    pub fn devices(&self) -> HashMap<HwAddr, DeviceInfo> {
+        self.devices.lock().unwrap().clone()
+    }
+
+    /// Stop the listener and sweeper threads and wait for them to exit.
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.listener_thread.take() {
+            let _ = handle.join();
+        }
+        if let Some(handle) = self.sweeper_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn spawn_listener(
+    socket: UdpSocket,
+    devices: Arc<Mutex<HashMap<HwAddr, DeviceInfo>>>,
+    running: Arc<AtomicBool>,
+    events_tx: Sender<RegistryEvent>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buf = [0u8; 84];
+        while running.load(Ordering::SeqCst) {
+            match socket.recv_from(&mut buf) {
+                Ok((amt, _src)) => {
+                    let header = match device::parse_header(&buf[..amt]) {
+                        Ok(header) => header,
+                        Err(e) => {
+                            warn!("dropping malformed beacon: {}", e);
+                            continue;
+                        }
+                    };
+                    let info = DeviceInfo {
+                        hw_addr: header.hw_addr(),
+                        ip_addr: header.ip_addr(),
+                        device_type: header.device_type(),
+                        delta_sequence: header.delta_sequence(),
+                        power_total: header.power_total(),
+                        update_period: header.update_period(),
+                        last_seen: Instant::now(),
+                    };
+
+                    let mut devices = devices.lock().unwrap();
+                    let is_new = !devices.contains_key(&info.hw_addr);
+                    devices.insert(info.hw_addr, info.clone());
+                    drop(devices);
+
+                    let event = if is_new {
+                        RegistryEvent::DeviceAdded(info)
+                    } else {
+                        RegistryEvent::DeviceUpdated(info)
+                    };
+                    let _ = events_tx.send(event);
+                }
+                Err(ref e)
+                    if e.kind() == std::io::ErrorKind::WouldBlock
+                        || e.kind() == std::io::ErrorKind::TimedOut =>
+                {
+                    continue
+                }
+                Err(e) => warn!("discovery socket error: {}", e),
+            }
+        }
+    })
+}
+
+fn spawn_sweeper(
+    staleness: Duration,
+    devices: Arc<Mutex<HashMap<HwAddr, DeviceInfo>>>,
+    running: Arc<AtomicBool>,
+    events_tx: Sender<RegistryEvent>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(250));
+
+            let mut devices = devices.lock().unwrap();
+            let stale = stale_devices(&devices, staleness);
+            for mac in &stale {
+                devices.remove(mac);
+                trace!("device {} went stale", mac);
+            }
+            drop(devices);
+
+            for mac in stale {
+                let _ = events_tx.send(RegistryEvent::DeviceRemoved(mac));
+            }
+        }
+    })
+}
+
+/// Hardware addresses of devices that haven't beaconed within `staleness`.
+fn stale_devices(devices: &HashMap<HwAddr, DeviceInfo>, staleness: Duration) -> Vec<HwAddr> {
+    devices
+        .iter()
+        .filter(|(_, info)| info.last_seen.elapsed() > staleness)
+        .map(|(mac, _)| *mac)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(hw_addr: HwAddr, last_seen: Instant) -> DeviceInfo {
+        DeviceInfo {
+            hw_addr,
+            ip_addr: Ipv4Addr::new(10, 0, 0, 1),
+            device_type: DeviceType::PIXELPUSHER,
+            delta_sequence: None,
+            power_total: None,
+            update_period: None,
+            last_seen,
+        }
+    }
+
+    #[test]
+    fn stale_devices_keeps_fresh_ones() {
+        let fresh = HwAddr::from(&[0, 0, 0, 0, 0, 1][..]);
+        let mut devices = HashMap::new();
+        devices.insert(fresh, device(fresh, Instant::now()));
+
+        assert!(stale_devices(&devices, Duration::from_secs(3)).is_empty());
+    }
+
+    #[test]
+    fn stale_devices_flags_expired_ones() {
+        let stale = HwAddr::from(&[0, 0, 0, 0, 0, 2][..]);
+        let fresh = HwAddr::from(&[0, 0, 0, 0, 0, 3][..]);
+        let mut devices = HashMap::new();
+        devices.insert(stale, device(stale, Instant::now() - Duration::from_secs(10)));
+        devices.insert(fresh, device(fresh, Instant::now()));
+
+        assert_eq!(stale_devices(&devices, Duration::from_secs(3)), vec![stale]);
+    }
+}